@@ -0,0 +1,210 @@
+//! A grapheme-cluster-aware cursor over a byte `PieceTable`.
+//!
+//! `PieceTable<'a, u8>`'s `insert`/`delete`/`delete_range` all take raw byte
+//! offsets, so editing at an arbitrary index can split a multi-byte UTF-8
+//! codepoint or sever a combining mark from its base character, corrupting
+//! the text. `GraphemeCursor` walks the table one codepoint at a time,
+//! grouping a base codepoint with any combining marks, variation selectors
+//! or zero-width joiners that follow it (and keeping a CRLF pair together)
+//! into a single cluster, and only ever reports or edits at the resulting
+//! cluster boundaries. This is a practical approximation of UAX #29, not a
+//! full table-driven implementation, but it covers the common cases a text
+//! editor needs and never produces an offset inside a codepoint.
+
+use crate::PieceTable;
+
+/// Codepoint ranges that the Unicode grapheme-cluster rules
+/// classify as combining marks, variation selectors or joiners: they attach
+/// to the preceding base codepoint instead of starting a new cluster.
+fn is_grapheme_extend(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F
+        | 0x0483..=0x0489
+        | 0x0591..=0x05BD | 0x05BF | 0x05C1..=0x05C2 | 0x05C4..=0x05C5 | 0x05C7
+        | 0x0610..=0x061A
+        | 0x064B..=0x065F | 0x0670
+        | 0x06D6..=0x06DC | 0x06DF..=0x06E4 | 0x06E7..=0x06E8 | 0x06EA..=0x06ED
+        | 0x0711 | 0x0730..=0x074A
+        | 0x07A6..=0x07B0
+        | 0x07EB..=0x07F3
+        | 0x0816..=0x0819 | 0x081B..=0x0823 | 0x0825..=0x0827 | 0x0829..=0x082D
+        | 0x0859..=0x085B
+        | 0x08E3..=0x0903
+        | 0x093A..=0x094F | 0x0951..=0x0957 | 0x0962..=0x0963
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200D
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+    )
+}
+
+/// Number of bytes in the UTF-8 encoding of a codepoint starting with
+/// `lead_byte`. Invalid lead bytes are treated as length 1 so decoding
+/// always makes forward progress instead of getting stuck.
+fn utf8_len(lead_byte: u8) -> usize {
+    if lead_byte & 0x80 == 0x00 {
+        1
+    } else if lead_byte & 0xE0 == 0xC0 {
+        2
+    } else if lead_byte & 0xF0 == 0xE0 {
+        3
+    } else if lead_byte & 0xF8 == 0xF0 {
+        4
+    } else {
+        1
+    }
+}
+
+/// A byte offset into a `PieceTable<u8>` that moves and edits in units of
+/// extended grapheme clusters rather than raw bytes.
+pub struct GraphemeCursor<'a, 'b> {
+    table: &'b mut PieceTable<'a, u8>,
+    position: usize,
+}
+
+impl<'a, 'b> GraphemeCursor<'a, 'b> {
+    /// Create a cursor positioned at the start of `table`.
+    pub fn new(table: &'b mut PieceTable<'a, u8>) -> Self {
+        GraphemeCursor { table, position: 0 }
+    }
+
+    /// The cursor's current byte offset. Always a grapheme-cluster boundary.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Move the cursor to `position` without checking that it lands on a
+    /// cluster boundary; callers should only pass offsets returned by this
+    /// cursor's own methods (e.g. a previously saved `position()`).
+    pub fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    fn byte_at(&self, offset: usize) -> u8 {
+        self.table.element_at(offset)
+    }
+
+    /// Decode the codepoint starting at `byte_pos`, returning its encoded
+    /// length and value. Malformed UTF-8 decodes as the replacement
+    /// character with length 1, so callers always make progress.
+    fn char_len_and_value(&self, byte_pos: usize) -> (usize, char) {
+        let lead = self.byte_at(byte_pos);
+        let len = utf8_len(lead).min(self.table.len() - byte_pos);
+
+        let mut buf = [0u8; 4];
+        for (i, slot) in buf[..len].iter_mut().enumerate() {
+            *slot = self.byte_at(byte_pos + i);
+        }
+
+        match std::str::from_utf8(&buf[..len]) {
+            Ok(s) if !s.is_empty() => (len, s.chars().next().unwrap()),
+            _ => (1, char::REPLACEMENT_CHARACTER),
+        }
+    }
+
+    /// Byte offset of the codepoint immediately preceding `byte_pos`, found
+    /// by walking back over UTF-8 continuation bytes.
+    fn prev_char_start(&self, byte_pos: usize) -> usize {
+        let mut i = byte_pos;
+        loop {
+            i -= 1;
+            if self.byte_at(i) & 0xC0 != 0x80 || i == 0 {
+                return i;
+            }
+        }
+    }
+
+    fn next_boundary_from(&self, from: usize) -> Option<usize> {
+        if from >= self.table.len() {
+            return None;
+        }
+
+        let (first_len, mut ch) = self.char_len_and_value(from);
+        let mut pos = from + first_len;
+
+        while pos < self.table.len() {
+            let (next_len, next_ch) = self.char_len_and_value(pos);
+
+            if is_grapheme_extend(next_ch) || (ch == '\r' && next_ch == '\n') {
+                pos += next_len;
+                ch = next_ch;
+                continue;
+            }
+
+            break;
+        }
+
+        Some(pos)
+    }
+
+    fn prev_boundary_from(&self, from: usize) -> Option<usize> {
+        if from == 0 {
+            return None;
+        }
+
+        let mut pos = from;
+
+        loop {
+            let start = self.prev_char_start(pos);
+            let (_, ch) = self.char_len_and_value(start);
+            pos = start;
+
+            if pos == 0 {
+                break;
+            }
+
+            if is_grapheme_extend(ch) {
+                continue;
+            }
+
+            if ch == '\n' {
+                let before = self.prev_char_start(pos);
+                let (_, prev_ch) = self.char_len_and_value(before);
+                if prev_ch == '\r' {
+                    pos = before;
+                }
+            }
+
+            break;
+        }
+
+        Some(pos)
+    }
+
+    /// Advance the cursor to the start of the next grapheme cluster.
+    /// Returns `None` (and leaves the cursor unmoved) if already at the end.
+    pub fn next_boundary(&mut self) -> Option<usize> {
+        let next = self.next_boundary_from(self.position)?;
+        self.position = next;
+        Some(next)
+    }
+
+    /// Move the cursor to the start of the previous grapheme cluster.
+    /// Returns `None` (and leaves the cursor unmoved) if already at the start.
+    pub fn prev_boundary(&mut self) -> Option<usize> {
+        let prev = self.prev_boundary_from(self.position)?;
+        self.position = prev;
+        Some(prev)
+    }
+
+    /// Insert `s` at the cursor and move the cursor past it.
+    pub fn insert_str(&mut self, s: &str) {
+        self.table.insert_slice(s.as_bytes(), self.position);
+        self.position += s.len();
+    }
+
+    /// Delete the grapheme cluster starting at the cursor, leaving the
+    /// cursor at the same byte offset (now the start of what followed it).
+    /// Returns `false` if the cursor is already at the end of the table.
+    pub fn delete_grapheme(&mut self) -> bool {
+        match self.next_boundary_from(self.position) {
+            Some(end) if end > self.position => {
+                self.table.delete_range(self.position..end);
+                true
+            }
+            _ => false,
+        }
+    }
+}
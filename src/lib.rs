@@ -2,33 +2,155 @@
 //!
 //! A piece table data structure implementation.
 
+use std::ops::Range;
+
+#[cfg(not(feature = "vec-pieces"))]
+mod tree;
+mod grapheme;
+mod snapshot;
+
+pub use grapheme::GraphemeCursor;
+pub use snapshot::SnapshotError;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum Buffer {
     Read,
     Add,
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 struct Piece {
     buffer: Buffer,
     start: usize,
     length: usize,
 }
 
+/// Backing store for a table's pieces. Defaults to the order-statistics
+/// `PieceTree` for O(log n) positional operations; small, short-lived
+/// documents where a handful of pieces never justify a tree's overhead can
+/// opt back into the flat `Vec<Piece>` with the `vec-pieces` feature.
+#[cfg(not(feature = "vec-pieces"))]
+type PieceStore = tree::PieceTree;
+
+#[cfg(feature = "vec-pieces")]
+type PieceStore = Vec<Piece>;
+
+#[cfg(not(feature = "vec-pieces"))]
+fn new_piece_store() -> PieceStore {
+    tree::PieceTree::new()
+}
+
+#[cfg(feature = "vec-pieces")]
+fn new_piece_store() -> PieceStore {
+    Vec::new()
+}
+
+#[cfg(not(feature = "vec-pieces"))]
+fn new_piece_store_with_capacity(_capacity: usize) -> PieceStore {
+    tree::PieceTree::new()
+}
+
+#[cfg(feature = "vec-pieces")]
+fn new_piece_store_with_capacity(capacity: usize) -> PieceStore {
+    Vec::with_capacity(capacity)
+}
+
+/// Find the piece covering logical `position`, returning its index among
+/// the pieces and the offset inside it. `PieceTree::locate` does this in
+/// O(log n) by descending on cached subtree lengths; the `vec-pieces`
+/// fallback still has to scan and accumulate lengths linearly.
+#[cfg(not(feature = "vec-pieces"))]
+fn locate_in_store(store: &PieceStore, position: usize) -> (usize, usize) {
+    store.locate(position)
+}
+
+#[cfg(feature = "vec-pieces")]
+fn locate_in_store(store: &PieceStore, position: usize) -> (usize, usize) {
+    let mut counter = 0;
+
+    for (i, piece) in store.iter().enumerate() {
+        if position < counter + piece.length {
+            return (i, position - counter);
+        }
+
+        counter += piece.length;
+    }
+
+    (store.len(), 0)
+}
+
+/// Update a piece's length in place. The default tree store doesn't expose
+/// `IndexMut` for this, since mutating `Piece::length` through a raw
+/// `&mut Piece` would leave the ancestors' cached `subtree_len` stale;
+/// `PieceTree::set_length` recomputes them on the way back to the root.
+/// The `vec-pieces` fallback has nothing to fix up.
+#[cfg(not(feature = "vec-pieces"))]
+fn set_piece_length(store: &mut PieceStore, index: usize, length: usize) {
+    store.set_length(index, length);
+}
+
+#[cfg(feature = "vec-pieces")]
+fn set_piece_length(store: &mut PieceStore, index: usize, length: usize) {
+    store[index].length = length;
+}
+
+/// Update a piece's start offset in place. `start` isn't part of any
+/// cached aggregate, so the tree store just writes through to the node.
+#[cfg(not(feature = "vec-pieces"))]
+fn set_piece_start(store: &mut PieceStore, index: usize, start: usize) {
+    store.set_start(index, start);
+}
+
+#[cfg(feature = "vec-pieces")]
+fn set_piece_start(store: &mut PieceStore, index: usize, start: usize) {
+    store[index].start = start;
+}
+
+/// A single reversible change to a `PieceTable`, recorded on the undo/redo
+/// stacks instead of a full snapshot of `pieces`.
+#[derive(Debug)]
+enum Edit<T> {
+    /// `length` elements were appended to `add_buf` starting at `add_start`
+    /// and spliced in at logical position `position`. Undoing only has to
+    /// drop the covering pieces; the bytes stay in `add_buf` so redoing can
+    /// re-point a piece at them instead of appending again.
+    Insert {
+        position: usize,
+        add_start: usize,
+        length: usize,
+    },
+    /// `elements` were removed from logical position `position`.
+    Delete { position: usize, elements: Vec<T> },
+    /// `removed` was replaced by `inserted_length` elements appended to
+    /// `add_buf` at `add_start`, both at logical position `position`.
+    Replace {
+        position: usize,
+        removed: Vec<T>,
+        add_start: usize,
+        inserted_length: usize,
+    },
+}
+
 #[derive(Debug)]
-pub struct PieceTable<'a, T: 'a + Clone> {
+pub struct PieceTable<'a, T: 'a + Clone + PartialEq> {
     read_buf: &'a [T],
     add_buf: Vec<T>,
-    pieces: Vec<Piece>,
+    pieces: PieceStore,
+    undo: Vec<Edit<T>>,
+    redo: Vec<Edit<T>>,
+    coalesce_inserts: bool,
+    line_separator: Option<T>,
+    line_starts: Vec<usize>,
+    line_index_dirty: bool,
 }
 
-pub struct Iter<'a, T: 'a + Clone> {
-    table: &'a PieceTable<'a, T>,
+pub struct Iter<'s, 'a: 's, T: 'a + Clone + PartialEq> {
+    table: &'s PieceTable<'a, T>,
     piece_idx: usize,
-    iter: std::slice::Iter<'a, T>,
+    iter: std::slice::Iter<'s, T>,
 }
 
-impl<'a, T: 'a + Clone> PieceTable<'a, T> {
+impl<'a, T: 'a + Clone + PartialEq> PieceTable<'a, T> {
     /// Create a new, empty PieceTable.
     ///
     /// # Examples
@@ -41,7 +163,13 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
         PieceTable {
             read_buf: &[],
             add_buf: vec![],
-            pieces: vec![],
+            pieces: new_piece_store(),
+            undo: vec![],
+            redo: vec![],
+            coalesce_inserts: false,
+            line_separator: None,
+            line_starts: vec![],
+            line_index_dirty: false,
         }
     }
 
@@ -58,7 +186,13 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
         PieceTable {
             read_buf: &[],
             add_buf: Vec::with_capacity(buffer_capacity),
-            pieces: Vec::with_capacity(piece_capacity),
+            pieces: new_piece_store_with_capacity(piece_capacity),
+            undo: vec![],
+            redo: vec![],
+            coalesce_inserts: false,
+            line_separator: None,
+            line_starts: vec![],
+            line_index_dirty: false,
         }
     }
 
@@ -84,6 +218,7 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
         }
 
         self.read_buf = src;
+        self.line_index_dirty = true;
     }
 
     /// Create new PieceTable using a base string as read_buffer.
@@ -97,14 +232,23 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
     pub fn from_str(buf: &str) -> PieceTable<u8> {
         let buf_size = buf.len();
 
+        let mut pieces = new_piece_store();
+        pieces.push(Piece {
+            buffer: Buffer::Read,
+            start: 0,
+            length: buf_size,
+        });
+
         PieceTable {
             read_buf: buf.as_bytes(),
             add_buf: vec![],
-            pieces: vec![Piece {
-                buffer: Buffer::Read,
-                start: 0,
-                length: buf_size,
-            }],
+            pieces,
+            undo: vec![],
+            redo: vec![],
+            coalesce_inserts: false,
+            line_separator: None,
+            line_starts: vec![],
+            line_index_dirty: false,
         }
     }
 
@@ -132,6 +276,14 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
     /// piece_table.insert(b'M', 11);
     /// ```
     pub fn insert(&mut self, element: T, index: usize) {
+        let add_start = self.add_buf.len();
+        self.insert_core(element, index);
+        self.push_insert_edit(index, add_start, 1);
+    }
+
+    fn insert_core(&mut self, element: T, index: usize) {
+        self.line_index_insert_one(index, &element);
+
         let append_buf_len = self.add_buf.len();
         let idx = self.find_piece_at_position(index);
         let is_border = self.position_is_at_border(index);
@@ -140,12 +292,11 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
 
         // check if insert is at the end of an append piece and is an extension of last added to append buffer
         if is_border && idx != 0 {
-            let prev_piece = &self.pieces[idx - 1];
-            if prev_piece.buffer == Buffer::Add {
-                if prev_piece.start + prev_piece.length == append_buf_len {
-                    self.pieces[idx - 1].length += 1;
-                    return;
-                }
+            let prev_piece = self.pieces[idx - 1];
+            if prev_piece.buffer == Buffer::Add && prev_piece.start + prev_piece.length == append_buf_len
+            {
+                set_piece_length(&mut self.pieces, idx - 1, prev_piece.length + 1);
+                return;
             }
         };
 
@@ -194,8 +345,18 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
     /// piece_table.insert_slice(b" Matias", 11);
     /// ```
     pub fn insert_slice(&mut self, slice: &[T], index: usize) {
+        if slice.is_empty() {
+            return;
+        }
+
+        let add_start = self.add_buf.len();
+        self.insert_slice_core(slice, index);
+        self.push_insert_edit(index, add_start, slice.len());
+    }
+
+    fn insert_slice_core(&mut self, slice: &[T], index: usize) {
         for (i, c) in slice.iter().enumerate() {
-            self.insert(c.clone(), index + i);
+            self.insert_core(c.clone(), index + i);
         }
     }
 
@@ -204,16 +365,7 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
             return 0;
         }
 
-        let mut counter = 0;
-        for (i, piece) in self.pieces.iter().enumerate() {
-            if position < counter + piece.length {
-                return i;
-            }
-
-            counter += piece.length;
-        }
-
-        self.pieces.len()
+        locate_in_store(&self.pieces, position).0
     }
 
     fn position_is_at_border(&self, position: usize) -> bool {
@@ -221,16 +373,7 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
             return true;
         }
 
-        let mut counter = 0;
-        for piece in self.pieces.iter() {
-            if position == piece.length + counter {
-                return true;
-            }
-
-            counter += piece.length;
-        }
-
-        false
+        locate_in_store(&self.pieces, position).1 == 0
     }
 
     /// Delete character at position 'char_index'.
@@ -248,17 +391,35 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
     /// piece_table.delete_char(9);
     /// ```
     pub fn delete(&mut self, index: usize) {
+        let removed = self.element_at(index);
+        self.delete_core(index);
+        self.push_delete_edit(index, vec![removed]);
+    }
+
+    fn element_at(&self, index: usize) -> T {
+        let (piece_index, index_in_piece) = self.split_piece_index_and_lenght(index);
+        let piece = &self.pieces[piece_index];
+        let buf: &[T] = match piece.buffer {
+            Buffer::Read => self.read_buf,
+            Buffer::Add => &self.add_buf,
+        };
+        buf[piece.start + index_in_piece].clone()
+    }
+
+    fn delete_core(&mut self, index: usize) {
+        self.line_index_remove(index..index + 1);
+
         let (piece_index, index_in_piece) = self.split_piece_index_and_lenght(index);
 
-        let mut piece = &mut self.pieces[piece_index];
+        let piece = self.pieces[piece_index];
 
         // deletes char at beggining of piece
         // only case of last remaining char in piece
         if index_in_piece == 0 {
-            piece.start += 1;
-            piece.length -= 1;
+            set_piece_start(&mut self.pieces, piece_index, piece.start + 1);
+            set_piece_length(&mut self.pieces, piece_index, piece.length - 1);
 
-            if piece.length == 0 {
+            if piece.length - 1 == 0 {
                 self.delete_and_join(piece_index);
             }
 
@@ -267,26 +428,21 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
 
         // deletes char at end of piece
         if index_in_piece == piece.length - 1 {
-            piece.length -= 1;
+            set_piece_length(&mut self.pieces, piece_index, piece.length - 1);
             return;
         }
 
         // deletes char in the middle of piece
         self.divide_piece(piece_index, index_in_piece);
 
-        self.pieces[piece_index + 1].start += 1;
-        self.pieces[piece_index + 1].length -= 1;
+        let next = self.pieces[piece_index + 1];
+        set_piece_start(&mut self.pieces, piece_index + 1, next.start + 1);
+        set_piece_length(&mut self.pieces, piece_index + 1, next.length - 1);
     }
 
     fn split_piece_index_and_lenght(&self, split_index: usize) -> (usize, usize) {
-        let mut counter: usize = 0;
-
-        for (i, piece) in self.pieces.iter().enumerate() {
-            if split_index < counter + piece.length {
-                return (i, split_index - counter);
-            }
-
-            counter += piece.length;
+        if split_index < self.len() {
+            return locate_in_store(&self.pieces, split_index);
         }
 
         (
@@ -326,41 +482,591 @@ impl<'a, T: 'a + Clone> PieceTable<'a, T> {
             return;
         }
 
-        let prev = &self.pieces[piece_index - 1];
-        let next = &self.pieces[piece_index];
+        let prev = self.pieces[piece_index - 1];
+        let next = self.pieces[piece_index];
 
         if prev.buffer == next.buffer && prev.start + prev.length == next.start {
-            self.pieces[piece_index - 1].length += next.length;
+            set_piece_length(&mut self.pieces, piece_index - 1, prev.length + next.length);
             self.pieces.remove(piece_index);
         }
     }
 
+    /// Remove the logical span `range` in one pass instead of repeatedly
+    /// calling `delete`.
+    ///
+    /// # Examples
+    /// ```
+    /// use piecetable::PieceTable;
+    ///
+    /// let mut piece_table = PieceTable::<u8>::from_str("Buenos dias amigo");
+    ///
+    /// piece_table.delete_range(7..12);
+    /// ```
+    pub fn delete_range(&mut self, range: Range<usize>) {
+        let removed = self.remove_range(range.clone());
+        self.push_delete_edit(range.start, removed);
+    }
+
+    /// Replace the logical span `range` with `slice` as a single edit.
+    ///
+    /// # Examples
+    /// ```
+    /// use piecetable::PieceTable;
+    ///
+    /// let mut piece_table = PieceTable::<u8>::from_str("Buenos dias amigo");
+    ///
+    /// piece_table.replace_range(7..12, b"tardes");
+    /// ```
+    pub fn replace_range(&mut self, range: Range<usize>, slice: &[T]) {
+        let removed = self.remove_range(range.clone());
+        let add_start = self.add_buf.len();
+        self.insert_slice_core(slice, range.start);
+        self.push_replace_edit(range.start, removed, add_start, slice.len());
+    }
+
+    /// Locate the piece covering logical `position` and the offset inside
+    /// it. Returns `(pieces.len(), 0)` when `position` is at (or past) the
+    /// end of the table.
+    fn locate_position(&self, position: usize) -> (usize, usize) {
+        locate_in_store(&self.pieces, position)
+    }
+
+    fn collect_range(&self, range: Range<usize>) -> Vec<T> {
+        let mut result = Vec::with_capacity(range.end - range.start);
+        let mut counter = 0;
+
+        for piece in self.pieces.iter() {
+            let piece_start = counter;
+            let piece_end = counter + piece.length;
+            counter = piece_end;
+
+            if piece_end <= range.start || piece_start >= range.end {
+                continue;
+            }
+
+            let lo = range.start.max(piece_start) - piece_start;
+            let hi = range.end.min(piece_end) - piece_start;
+            let buf: &[T] = match piece.buffer {
+                Buffer::Read => self.read_buf,
+                Buffer::Add => &self.add_buf,
+            };
+            result.extend_from_slice(&buf[piece.start + lo..piece.start + hi]);
+        }
+
+        result
+    }
+
+    /// Merge `pieces[index]` with `pieces[index + 1]` if they are
+    /// contiguous slices of the same buffer, i.e. collapse the seam left
+    /// behind by a split or a range removal.
+    fn merge_with_next(&mut self, index: usize) {
+        if index + 1 >= self.pieces.len() {
+            return;
+        }
+
+        let piece = self.pieces[index];
+        let next = self.pieces[index + 1];
+
+        if next.buffer == piece.buffer && piece.start + piece.length == next.start {
+            set_piece_length(&mut self.pieces, index, piece.length + next.length);
+            self.pieces.remove(index + 1);
+        }
+    }
+
+    fn remove_range(&mut self, range: Range<usize>) -> Vec<T> {
+        if range.start >= range.end {
+            return Vec::new();
+        }
+
+        self.line_index_remove(range.clone());
+
+        let removed = self.collect_range(range.clone());
+
+        let (start_idx, start_offset) = self.locate_position(range.start);
+        let (end_idx, end_offset) = self.locate_position(range.end);
+
+        if start_idx == end_idx {
+            if start_idx < self.pieces.len() {
+                let piece = self.pieces.remove(start_idx);
+                let mut insert_at = start_idx;
+
+                if start_offset > 0 {
+                    self.pieces.insert(
+                        insert_at,
+                        Piece {
+                            buffer: piece.buffer,
+                            start: piece.start,
+                            length: start_offset,
+                        },
+                    );
+                    insert_at += 1;
+                }
+
+                let tail_length = piece.length - end_offset;
+                if tail_length > 0 {
+                    self.pieces.insert(
+                        insert_at,
+                        Piece {
+                            buffer: piece.buffer,
+                            start: piece.start + end_offset,
+                            length: tail_length,
+                        },
+                    );
+                }
+            }
+        } else {
+            let drain_start = if start_offset > 0 {
+                start_idx + 1
+            } else {
+                start_idx
+            };
+            self.pieces.drain(drain_start..end_idx);
+
+            if end_offset > 0 && drain_start < self.pieces.len() {
+                let piece = self.pieces[drain_start];
+                set_piece_start(&mut self.pieces, drain_start, piece.start + end_offset);
+                set_piece_length(&mut self.pieces, drain_start, piece.length - end_offset);
+            }
+
+            if start_offset > 0 {
+                set_piece_length(&mut self.pieces, start_idx, start_offset);
+            }
+        }
+
+        // the boundary pieces may now be contiguous with their neighbours
+        if start_idx > 0 {
+            self.merge_with_next(start_idx - 1);
+        }
+        self.merge_with_next(start_idx);
+
+        removed
+    }
+
+    /// Re-point a piece at an already-present `add_buf` range instead of
+    /// appending new bytes, mirroring `insert_core` without growing `add_buf`.
+    fn reinsert_span(&mut self, position: usize, add_start: usize, length: usize) {
+        let reinserted = self.add_buf[add_start..add_start + length].to_vec();
+        self.line_index_insert(position, &reinserted);
+
+        let idx = self.find_piece_at_position(position);
+        let is_border = self.position_is_at_border(position);
+
+        if is_border && idx != 0 {
+            let prev_piece = self.pieces[idx - 1];
+            if prev_piece.buffer == Buffer::Add && prev_piece.start + prev_piece.length == add_start
+            {
+                set_piece_length(&mut self.pieces, idx - 1, prev_piece.length + length);
+                return;
+            }
+        }
+
+        if is_border {
+            self.pieces.insert(
+                idx,
+                Piece {
+                    buffer: Buffer::Add,
+                    start: add_start,
+                    length,
+                },
+            );
+            return;
+        }
+
+        let mut counter = 0;
+        for i in 0..idx {
+            counter += self.pieces[i].length;
+        }
+        let split_index = position - counter;
+
+        self.divide_piece(idx, split_index);
+        self.pieces.insert(
+            idx + 1,
+            Piece {
+                buffer: Buffer::Add,
+                start: add_start,
+                length,
+            },
+        );
+    }
+
+    fn push_insert_edit(&mut self, position: usize, add_start: usize, length: usize) {
+        if length == 0 {
+            return;
+        }
+
+        self.redo.clear();
+
+        if self.coalesce_inserts {
+            if let Some(Edit::Insert {
+                position: last_position,
+                add_start: last_add_start,
+                length: last_length,
+            }) = self.undo.last_mut()
+            {
+                if length == 1
+                    && *last_position + *last_length == position
+                    && *last_add_start + *last_length == add_start
+                {
+                    *last_length += 1;
+                    return;
+                }
+            }
+        }
+
+        self.undo.push(Edit::Insert {
+            position,
+            add_start,
+            length,
+        });
+    }
+
+    fn push_delete_edit(&mut self, position: usize, elements: Vec<T>) {
+        if elements.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+        self.undo.push(Edit::Delete { position, elements });
+    }
+
+    fn push_replace_edit(
+        &mut self,
+        position: usize,
+        removed: Vec<T>,
+        add_start: usize,
+        inserted_length: usize,
+    ) {
+        if removed.is_empty() && inserted_length == 0 {
+            return;
+        }
+
+        self.redo.clear();
+        self.undo.push(Edit::Replace {
+            position,
+            removed,
+            add_start,
+            inserted_length,
+        });
+    }
+
+    /// Enable or disable merging consecutive single-element inserts at
+    /// adjacent positions into one undo step, so typing a word undoes in a
+    /// single call.
+    pub fn set_coalesce_inserts(&mut self, enabled: bool) {
+        self.coalesce_inserts = enabled;
+    }
+
+    /// Whether there is an edit available to [`PieceTable::undo`].
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Whether there is an edit available to [`PieceTable::redo`].
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Undo the last edit, moving it onto the redo stack.
+    ///
+    /// Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(Edit::Insert {
+                position,
+                add_start,
+                length,
+            }) => {
+                self.remove_range(position..position + length);
+                self.redo.push(Edit::Insert {
+                    position,
+                    add_start,
+                    length,
+                });
+                true
+            }
+            Some(Edit::Delete { position, elements }) => {
+                self.insert_slice_core(&elements, position);
+                self.redo.push(Edit::Delete { position, elements });
+                true
+            }
+            Some(Edit::Replace {
+                position,
+                removed,
+                add_start,
+                inserted_length,
+            }) => {
+                self.remove_range(position..position + inserted_length);
+                self.insert_slice_core(&removed, position);
+                self.redo.push(Edit::Replace {
+                    position,
+                    removed,
+                    add_start,
+                    inserted_length,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the last undone edit, moving it back onto the undo stack.
+    ///
+    /// Returns `false` if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(Edit::Insert {
+                position,
+                add_start,
+                length,
+            }) => {
+                self.reinsert_span(position, add_start, length);
+                self.undo.push(Edit::Insert {
+                    position,
+                    add_start,
+                    length,
+                });
+                true
+            }
+            Some(Edit::Delete { position, elements }) => {
+                self.remove_range(position..position + elements.len());
+                self.undo.push(Edit::Delete { position, elements });
+                true
+            }
+            Some(Edit::Replace {
+                position,
+                removed,
+                add_start,
+                inserted_length,
+            }) => {
+                self.remove_range(position..position + removed.len());
+                self.reinsert_span(position, add_start, inserted_length);
+                self.undo.push(Edit::Replace {
+                    position,
+                    removed,
+                    add_start,
+                    inserted_length,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Turn on the logical line index, using `separator` to mark line
+    /// boundaries (typically `b'\n'` for a `PieceTable<u8>`), and build it
+    /// from the current content.
+    pub fn enable_line_index(&mut self, separator: T) {
+        self.line_separator = Some(separator);
+        self.rebuild_line_index();
+    }
+
+    /// Turn off the logical line index and free the offsets it held.
+    pub fn disable_line_index(&mut self) {
+        self.line_separator = None;
+        self.line_starts.clear();
+        self.line_index_dirty = false;
+    }
+
+    /// Number of logical lines, i.e. one more than the number of separators,
+    /// or 0 while the line index is disabled.
+    pub fn line_count(&mut self) -> usize {
+        self.ensure_line_index();
+        self.line_starts.len()
+    }
+
+    /// Logical offset at which `line` starts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line >= line_count()`, the same contract as indexing a
+    /// slice out of bounds.
+    pub fn line_to_offset(&mut self, line: usize) -> usize {
+        self.ensure_line_index();
+        assert!(
+            line < self.line_starts.len(),
+            "line {} out of bounds (line_count() is {})",
+            line,
+            self.line_starts.len()
+        );
+        self.line_starts[line]
+    }
+
+    /// Convert a logical offset into a (line, column) pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the line index is empty, i.e. `enable_line_index` was
+    /// never called.
+    pub fn offset_to_line_col(&mut self, position: usize) -> (usize, usize) {
+        self.ensure_line_index();
+        assert!(
+            !self.line_starts.is_empty(),
+            "line index is disabled; call enable_line_index first"
+        );
+        let line = match self.line_starts.binary_search(&position) {
+            Ok(line) => line,
+            Err(insert_at) => insert_at - 1,
+        };
+        (line, position - self.line_starts[line])
+    }
+
+    /// Iterate the elements of `line`, excluding its trailing separator.
+    ///
+    /// Borrows `self` for no longer than the returned iterator lives (not
+    /// for the table's own `'a`), so the table is usable again once it's
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line >= line_count()`, the same contract as indexing a
+    /// slice out of bounds.
+    pub fn line_slice(&mut self, line: usize) -> impl Iterator<Item = &T> + '_ {
+        self.ensure_line_index();
+        assert!(
+            line < self.line_starts.len(),
+            "line {} out of bounds (line_count() is {})",
+            line,
+            self.line_starts.len()
+        );
+
+        let start = self.line_starts[line];
+        let end = if line + 1 < self.line_starts.len() {
+            self.line_starts[line + 1] - 1
+        } else {
+            self.len()
+        };
+
+        self.iter().skip(start).take(end - start)
+    }
+
+    /// Rebuild `line_starts` if the table was mutated through an API (e.g.
+    /// `src`) that bypassed the incremental index maintenance.
+    fn ensure_line_index(&mut self) {
+        if self.line_index_dirty {
+            self.rebuild_line_index();
+        }
+    }
+
+    fn rebuild_line_index(&mut self) {
+        self.line_index_dirty = false;
+        self.line_starts.clear();
+
+        let separator = match &self.line_separator {
+            Some(separator) => separator.clone(),
+            None => return,
+        };
+
+        self.line_starts.push(0);
+        let mut offset = 0;
+
+        for piece in self.pieces.iter() {
+            let buf: &[T] = match piece.buffer {
+                Buffer::Read => self.read_buf,
+                Buffer::Add => &self.add_buf,
+            };
+
+            for element in &buf[piece.start..piece.start + piece.length] {
+                offset += 1;
+                if *element == separator {
+                    self.line_starts.push(offset);
+                }
+            }
+        }
+    }
+
+    /// Incrementally update `line_starts` for a single inserted element,
+    /// shifting later line starts and recording a new one if it is a
+    /// separator.
+    fn line_index_insert_one(&mut self, index: usize, element: &T) {
+        let elements = std::slice::from_ref(element);
+        self.line_index_insert(index, elements);
+    }
+
+    fn line_index_insert(&mut self, index: usize, elements: &[T]) {
+        if self.line_index_dirty {
+            return;
+        }
+
+        let separator = match &self.line_separator {
+            Some(separator) => separator.clone(),
+            None => return,
+        };
+
+        let shift = elements.len();
+        let split_at = self.line_starts.partition_point(|&start| start <= index);
+        for start in &mut self.line_starts[split_at..] {
+            *start += shift;
+        }
+
+        let new_starts: Vec<usize> = elements
+            .iter()
+            .enumerate()
+            .filter(|(_, element)| **element == separator)
+            .map(|(i, _)| index + i + 1)
+            .collect();
+
+        if !new_starts.is_empty() {
+            self.line_starts.splice(split_at..split_at, new_starts);
+        }
+    }
+
+    /// Incrementally update `line_starts` after removing the logical span
+    /// `range`: drop any line start it swallowed and shift the rest left.
+    fn line_index_remove(&mut self, range: Range<usize>) {
+        if self.line_index_dirty || self.line_separator.is_none() || range.start >= range.end {
+            return;
+        }
+
+        let shift = range.end - range.start;
+        self.line_starts
+            .retain(|&start| start == 0 || start <= range.start || start > range.end);
+
+        for start in &mut self.line_starts {
+            if *start > range.end {
+                *start -= shift;
+            }
+        }
+    }
+
     /// Get total length of piece table.
     pub fn len(&self) -> usize {
         self.pieces.iter().map(|x| x.length).sum()
     }
 
-    fn get_buffer(&'a self, piece: &Piece) -> &'a [T] {
+    fn get_buffer(&self, piece: &Piece) -> &[T] {
         match piece.buffer {
             Buffer::Read => self.read_buf,
             Buffer::Add => &self.add_buf,
         }
     }
 
-    pub fn iter(&'a self) -> Iter<'a, T> {
+    /// Iterate this table's elements in logical order.
+    ///
+    /// Unlike a method whose return value borrows `self` for the table's
+    /// own `'a` (the read-buffer lifetime), this borrows `self` for no
+    /// longer than the returned iterator lives, so `self` is usable again
+    /// as soon as it's dropped.
+    pub fn iter(&self) -> Iter<'_, 'a, T> {
         let piece = &self.pieces[0];
         let buf = self.get_buffer(piece);
         let iter = buf[piece.start .. piece.start + piece.length].iter();
         Iter {
-            table: &self,
+            table: self,
             piece_idx: 0,
             iter,
         }
     }
 }
 
-impl<'a, T: 'a + Clone> Iterator for Iter<'a, T> {
-    type Item = &'a T;
+impl<'a> PieceTable<'a, u8> {
+    /// Turn on the logical line index using `b'\n'` as the separator.
+    pub fn enable_default_line_index(&mut self) {
+        self.enable_line_index(b'\n');
+    }
+}
+
+impl<'s, 'a: 's, T: 'a + Clone + PartialEq> Iterator for Iter<'s, 'a, T> {
+    type Item = &'s T;
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(next) = self.iter.next() {
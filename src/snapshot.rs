@@ -0,0 +1,386 @@
+//! Serializing a `PieceTable<u8>`'s content and internal piece layout.
+//!
+//! A naive snapshot would just flatten the table to one blob and reload it
+//! as a single piece, losing the append-buffer locality (and any undo/redo
+//! state resting on `add_buf` offsets) that made the table efficient to
+//! edit in the first place. Instead this serializes the pieces themselves
+//! (buffer tag, start, length) alongside `add_buf`, plus a marker for the
+//! length of the read-only source the caller must supply again on load, so
+//! reloading against that same source reconstructs the exact piece layout.
+//!
+//! The wire format is a small custom binary layout (little-endian `u64`
+//! lengths throughout, chosen over `usize` so a snapshot is portable across
+//! 32- and 64-bit builds):
+//!
+//! ```text
+//! read_buf_len: u64
+//! add_buf_len:  u64
+//! add_buf:      [u8; add_buf_len]
+//! logical_len:  u64
+//! piece_count:  u64
+//! pieces:       [{ tag: u8, start: u64, length: u64 }; piece_count]
+//! undo_count:   u64
+//! undo:         [Edit; undo_count]
+//! redo_count:   u64
+//! redo:         [Edit; redo_count]
+//! ```
+//!
+//! `logical_len` is the table's total length at the time of the snapshot;
+//! `from_bytes` checks it against the sum of the reloaded pieces' lengths
+//! so a truncated piece list is rejected instead of silently producing a
+//! shorter document. Piece order is not checked: reordered pieces keep the
+//! same total length and load without error. The undo/redo stacks are serialized too,
+//! so a restored table can still be undone past the point of the snapshot.
+//! An `Edit` is `{ tag: u8, position: u64, ... }`, the `...` depending on
+//! the tag: 0 (`Insert`) is `add_start: u64, length: u64`; 1 (`Delete`) is
+//! `elements_len: u64, elements: [u8; elements_len]`; 2 (`Replace`) is
+//! `removed_len: u64, removed: [u8; removed_len], add_start: u64,
+//! inserted_length: u64`. An `Insert`/`Replace` edit's `add_start + length`
+//! is bounds-checked against `add_buf`, same as a piece's span against its
+//! buffer, since redoing it would otherwise index `add_buf` directly.
+//!
+//! The coalescing flag and the line index are not part of the snapshot: a
+//! loaded table starts with coalescing off and the line index disabled,
+//! same as one built with `new` or `from_str`.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::{new_piece_store_with_capacity, Buffer, Edit, Piece, PieceTable};
+
+/// Why a snapshot could not be loaded.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The byte stream ended before a complete snapshot was read.
+    UnexpectedEof,
+    /// The supplied read-only buffer's length doesn't match the one the
+    /// snapshot was taken against, so its pieces would point at the wrong
+    /// bytes.
+    ReadBufferLengthMismatch { expected: usize, found: usize },
+    /// A declared buffer tag was neither 0 (read buffer) nor 1 (add buffer).
+    InvalidBufferTag(u8),
+    /// Piece `index`'s `start + length` falls outside the buffer it targets.
+    PieceOutOfBounds { index: usize },
+    /// The reloaded pieces' total length doesn't match the `logical_len`
+    /// the snapshot was taken with, so the piece list is incomplete or
+    /// corrupt even though every individual piece looked in-bounds.
+    LogicalLengthMismatch { expected: usize, found: usize },
+    /// A declared edit tag was not 0 (`Insert`), 1 (`Delete`), or 2 (`Replace`).
+    InvalidEditTag(u8),
+    /// Undo/redo edit `index`'s `add_start + length` falls outside `add_buf`.
+    EditOutOfBounds { index: usize },
+    /// Accumulating piece or offset lengths would overflow `usize`.
+    LengthOverflow,
+    /// Reading the underlying stream failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::UnexpectedEof => write!(f, "unexpected end of snapshot data"),
+            SnapshotError::ReadBufferLengthMismatch { expected, found } => write!(
+                f,
+                "read buffer length mismatch: snapshot expects {} bytes, got {}",
+                expected, found
+            ),
+            SnapshotError::InvalidBufferTag(tag) => write!(f, "invalid buffer tag: {}", tag),
+            SnapshotError::PieceOutOfBounds { index } => {
+                write!(f, "piece {} is out of bounds for its buffer", index)
+            }
+            SnapshotError::LogicalLengthMismatch { expected, found } => write!(
+                f,
+                "logical length mismatch: snapshot expects {} elements, pieces cover {}",
+                expected, found
+            ),
+            SnapshotError::InvalidEditTag(tag) => write!(f, "invalid edit tag: {}", tag),
+            SnapshotError::EditOutOfBounds { index } => {
+                write!(f, "edit {} is out of bounds for the add buffer", index)
+            }
+            SnapshotError::LengthOverflow => write!(f, "snapshot lengths overflow usize"),
+            SnapshotError::Io(err) => write!(f, "snapshot I/O error: {}", err),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+fn read_u8(bytes: &[u8], cursor: &mut usize) -> Result<u8, SnapshotError> {
+    let byte = read_bytes(bytes, cursor, 1)?[0];
+    Ok(byte)
+}
+
+fn read_u64(bytes: &[u8], cursor: &mut usize) -> Result<u64, SnapshotError> {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(read_bytes(bytes, cursor, 8)?);
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes<'b>(bytes: &'b [u8], cursor: &mut usize, len: usize) -> Result<&'b [u8], SnapshotError> {
+    let end = cursor.checked_add(len).ok_or(SnapshotError::LengthOverflow)?;
+    let slice = bytes.get(*cursor..end).ok_or(SnapshotError::UnexpectedEof)?;
+    *cursor = end;
+    Ok(slice)
+}
+
+fn write_bytes_with_len(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn read_bytes_with_len(bytes: &[u8], cursor: &mut usize) -> Result<Vec<u8>, SnapshotError> {
+    let len = read_u64(bytes, cursor)? as usize;
+    Ok(read_bytes(bytes, cursor, len)?.to_vec())
+}
+
+fn write_edit(out: &mut Vec<u8>, edit: &Edit<u8>) {
+    match edit {
+        Edit::Insert {
+            position,
+            add_start,
+            length,
+        } => {
+            out.push(0);
+            out.extend_from_slice(&(*position as u64).to_le_bytes());
+            out.extend_from_slice(&(*add_start as u64).to_le_bytes());
+            out.extend_from_slice(&(*length as u64).to_le_bytes());
+        }
+        Edit::Delete { position, elements } => {
+            out.push(1);
+            out.extend_from_slice(&(*position as u64).to_le_bytes());
+            write_bytes_with_len(out, elements);
+        }
+        Edit::Replace {
+            position,
+            removed,
+            add_start,
+            inserted_length,
+        } => {
+            out.push(2);
+            out.extend_from_slice(&(*position as u64).to_le_bytes());
+            write_bytes_with_len(out, removed);
+            out.extend_from_slice(&(*add_start as u64).to_le_bytes());
+            out.extend_from_slice(&(*inserted_length as u64).to_le_bytes());
+        }
+    }
+}
+
+/// Check that `add_start + length` (an undo/redo `Edit`'s span into
+/// `add_buf`) is in bounds, the same way piece spans are checked against
+/// their buffer in `from_bytes`. Replaying an out-of-bounds span via
+/// `reinsert_span` would index `add_buf` directly and panic.
+fn check_add_span(
+    add_start: usize,
+    length: usize,
+    add_buf_len: usize,
+    index: usize,
+) -> Result<(), SnapshotError> {
+    let end = add_start
+        .checked_add(length)
+        .ok_or(SnapshotError::LengthOverflow)?;
+    if end > add_buf_len {
+        return Err(SnapshotError::EditOutOfBounds { index });
+    }
+    Ok(())
+}
+
+fn read_edit(bytes: &[u8], cursor: &mut usize, add_buf_len: usize, index: usize) -> Result<Edit<u8>, SnapshotError> {
+    let tag = read_u8(bytes, cursor)?;
+    let position = read_u64(bytes, cursor)? as usize;
+
+    match tag {
+        0 => {
+            let add_start = read_u64(bytes, cursor)? as usize;
+            let length = read_u64(bytes, cursor)? as usize;
+            check_add_span(add_start, length, add_buf_len, index)?;
+            Ok(Edit::Insert {
+                position,
+                add_start,
+                length,
+            })
+        }
+        1 => {
+            let elements = read_bytes_with_len(bytes, cursor)?;
+            Ok(Edit::Delete { position, elements })
+        }
+        2 => {
+            let removed = read_bytes_with_len(bytes, cursor)?;
+            let add_start = read_u64(bytes, cursor)? as usize;
+            let inserted_length = read_u64(bytes, cursor)? as usize;
+            check_add_span(add_start, inserted_length, add_buf_len, index)?;
+            Ok(Edit::Replace {
+                position,
+                removed,
+                add_start,
+                inserted_length,
+            })
+        }
+        tag => Err(SnapshotError::InvalidEditTag(tag)),
+    }
+}
+
+fn write_edits(out: &mut Vec<u8>, edits: &[Edit<u8>]) {
+    out.extend_from_slice(&(edits.len() as u64).to_le_bytes());
+    for edit in edits {
+        write_edit(out, edit);
+    }
+}
+
+fn read_edits(bytes: &[u8], cursor: &mut usize, add_buf_len: usize) -> Result<Vec<Edit<u8>>, SnapshotError> {
+    let count = read_u64(bytes, cursor)? as usize;
+    let mut edits = Vec::with_capacity(count);
+    for index in 0..count {
+        edits.push(read_edit(bytes, cursor, add_buf_len, index)?);
+    }
+    Ok(edits)
+}
+
+impl<'a> PieceTable<'a, u8> {
+    /// Serialize this table's `add_buf` and piece layout to a byte vector.
+    ///
+    /// # Examples
+    /// ```
+    /// use piecetable::PieceTable;
+    ///
+    /// let piece_table = PieceTable::<u8>::from_str("Buenos dias");
+    /// let bytes = piece_table.to_bytes();
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        out.extend_from_slice(&(self.read_buf.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(self.add_buf.len() as u64).to_le_bytes());
+        out.extend_from_slice(&self.add_buf);
+
+        out.extend_from_slice(&(self.len() as u64).to_le_bytes());
+
+        out.extend_from_slice(&(self.pieces.len() as u64).to_le_bytes());
+        for piece in self.pieces.iter() {
+            out.push(match piece.buffer {
+                Buffer::Read => 0,
+                Buffer::Add => 1,
+            });
+            out.extend_from_slice(&(piece.start as u64).to_le_bytes());
+            out.extend_from_slice(&(piece.length as u64).to_le_bytes());
+        }
+
+        write_edits(&mut out, &self.undo);
+        write_edits(&mut out, &self.redo);
+
+        out
+    }
+
+    /// Reconstruct a table from a snapshot produced by [`to_bytes`], reading
+    /// its pieces' read-buffer spans against `read_buf`. Returns an error
+    /// instead of panicking if `bytes` is truncated, `read_buf` doesn't
+    /// match the length the snapshot was taken against, or a piece falls
+    /// outside the buffer it targets.
+    ///
+    /// [`to_bytes`]: PieceTable::to_bytes
+    ///
+    /// # Examples
+    /// ```
+    /// use piecetable::PieceTable;
+    ///
+    /// let source = "Buenos dias";
+    /// let piece_table = PieceTable::<u8>::from_str(source);
+    /// let bytes = piece_table.to_bytes();
+    ///
+    /// let restored = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap();
+    /// ```
+    pub fn from_bytes(bytes: &[u8], read_buf: &'a [u8]) -> Result<PieceTable<'a, u8>, SnapshotError> {
+        let mut cursor = 0;
+
+        let expected_read_len = read_u64(bytes, &mut cursor)? as usize;
+        if expected_read_len != read_buf.len() {
+            return Err(SnapshotError::ReadBufferLengthMismatch {
+                expected: expected_read_len,
+                found: read_buf.len(),
+            });
+        }
+
+        let add_buf_len = read_u64(bytes, &mut cursor)? as usize;
+        let add_buf = read_bytes(bytes, &mut cursor, add_buf_len)?.to_vec();
+
+        let expected_len = read_u64(bytes, &mut cursor)? as usize;
+
+        let piece_count = read_u64(bytes, &mut cursor)? as usize;
+        let mut pieces = new_piece_store_with_capacity(piece_count);
+        let mut total_len: usize = 0;
+
+        for index in 0..piece_count {
+            let buffer = match read_u8(bytes, &mut cursor)? {
+                0 => Buffer::Read,
+                1 => Buffer::Add,
+                tag => return Err(SnapshotError::InvalidBufferTag(tag)),
+            };
+            let start = read_u64(bytes, &mut cursor)? as usize;
+            let length = read_u64(bytes, &mut cursor)? as usize;
+
+            let end = start.checked_add(length).ok_or(SnapshotError::LengthOverflow)?;
+            let bound = match buffer {
+                Buffer::Read => read_buf.len(),
+                Buffer::Add => add_buf.len(),
+            };
+            if end > bound {
+                return Err(SnapshotError::PieceOutOfBounds { index });
+            }
+
+            total_len = total_len
+                .checked_add(length)
+                .ok_or(SnapshotError::LengthOverflow)?;
+
+            pieces.push(Piece {
+                buffer,
+                start,
+                length,
+            });
+        }
+
+        if total_len != expected_len {
+            return Err(SnapshotError::LogicalLengthMismatch {
+                expected: expected_len,
+                found: total_len,
+            });
+        }
+
+        let undo = read_edits(bytes, &mut cursor, add_buf.len())?;
+        let redo = read_edits(bytes, &mut cursor, add_buf.len())?;
+
+        Ok(PieceTable {
+            read_buf,
+            add_buf,
+            pieces,
+            undo,
+            redo,
+            coalesce_inserts: false,
+            line_separator: None,
+            line_starts: vec![],
+            line_index_dirty: false,
+        })
+    }
+
+    /// Write this table's snapshot (see [`to_bytes`]) to `writer`.
+    ///
+    /// [`to_bytes`]: PieceTable::to_bytes
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    /// Read a snapshot (see [`from_bytes`]) from `reader`.
+    ///
+    /// [`from_bytes`]: PieceTable::from_bytes
+    pub fn read_from<R: Read>(reader: &mut R, read_buf: &'a [u8]) -> Result<PieceTable<'a, u8>, SnapshotError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(SnapshotError::Io)?;
+        Self::from_bytes(&bytes, read_buf)
+    }
+}
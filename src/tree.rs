@@ -0,0 +1,332 @@
+//! An implicit, order-statistics AVL tree of pieces.
+//!
+//! Every positional operation on the flat `Vec<Piece>` (`find_piece_at_position`,
+//! `position_is_at_border`, `split_piece_index_and_lenght`, insert, remove)
+//! has to walk the whole piece list summing lengths, so each edit is
+//! O(number of pieces). `PieceTree` keeps the same pieces in the same
+//! in-order sequence, but as a height-balanced tree where every node caches
+//! its subtree's piece count (`subtree_size`) and total logical length
+//! (`subtree_len`). Descending the tree toward a logical position or a
+//! piece-array index only ever has to look at one child per level, so
+//! lookup, insert, and remove are all O(log n).
+//!
+//! The public surface intentionally mirrors `Vec<Piece>` (`len`, `push`,
+//! `insert`, `remove`, `drain`, `iter`, `last`, indexing) so it's a drop-in
+//! replacement for the piece storage in `PieceTable`.
+
+use std::cmp::max;
+use std::ops::{Index, Range};
+
+use crate::Piece;
+
+#[derive(Debug)]
+struct Node {
+    piece: Piece,
+    left: Option<Box<Node>>,
+    right: Option<Box<Node>>,
+    height: i32,
+    subtree_size: usize,
+    subtree_len: usize,
+}
+
+impl Node {
+    fn new(piece: Piece) -> Self {
+        let length = piece.length;
+        Node {
+            piece,
+            left: None,
+            right: None,
+            height: 1,
+            subtree_size: 1,
+            subtree_len: length,
+        }
+    }
+
+    fn height(node: &Option<Box<Node>>) -> i32 {
+        node.as_ref().map_or(0, |n| n.height)
+    }
+
+    fn size(node: &Option<Box<Node>>) -> usize {
+        node.as_ref().map_or(0, |n| n.subtree_size)
+    }
+
+    fn subtree_len(node: &Option<Box<Node>>) -> usize {
+        node.as_ref().map_or(0, |n| n.subtree_len)
+    }
+
+    fn update(&mut self) {
+        self.height = 1 + max(Self::height(&self.left), Self::height(&self.right));
+        self.subtree_size = 1 + Self::size(&self.left) + Self::size(&self.right);
+        self.subtree_len =
+            self.piece.length + Self::subtree_len(&self.left) + Self::subtree_len(&self.right);
+    }
+
+    fn balance_factor(&self) -> i32 {
+        Self::height(&self.left) - Self::height(&self.right)
+    }
+
+    fn rotate_left(mut self: Box<Self>) -> Box<Self> {
+        let mut new_root = self.right.take().expect("rotate_left needs a right child");
+        self.right = new_root.left.take();
+        self.update();
+        new_root.left = Some(self);
+        new_root.update();
+        new_root
+    }
+
+    fn rotate_right(mut self: Box<Self>) -> Box<Self> {
+        let mut new_root = self.left.take().expect("rotate_right needs a left child");
+        self.left = new_root.right.take();
+        self.update();
+        new_root.right = Some(self);
+        new_root.update();
+        new_root
+    }
+
+    fn rebalance(mut self: Box<Self>) -> Box<Self> {
+        self.update();
+
+        let balance = self.balance_factor();
+
+        if balance > 1 {
+            if self.left.as_ref().unwrap().balance_factor() < 0 {
+                self.left = Some(self.left.take().unwrap().rotate_left());
+            }
+            return self.rotate_right();
+        }
+
+        if balance < -1 {
+            if self.right.as_ref().unwrap().balance_factor() > 0 {
+                self.right = Some(self.right.take().unwrap().rotate_right());
+            }
+            return self.rotate_left();
+        }
+
+        self
+    }
+
+    fn insert_at(node: Option<Box<Node>>, index: usize, piece: Piece) -> Box<Node> {
+        match node {
+            None => Box::new(Node::new(piece)),
+            Some(mut n) => {
+                let left_size = Self::size(&n.left);
+                if index <= left_size {
+                    n.left = Some(Self::insert_at(n.left.take(), index, piece));
+                } else {
+                    n.right = Some(Self::insert_at(n.right.take(), index - left_size - 1, piece));
+                }
+                n.rebalance()
+            }
+        }
+    }
+
+    fn remove_at(node: Option<Box<Node>>, index: usize) -> (Option<Box<Node>>, Piece) {
+        let mut n = node.expect("piece index out of bounds");
+        let left_size = Self::size(&n.left);
+
+        if index < left_size {
+            let (left, removed) = Self::remove_at(n.left.take(), index);
+            n.left = left;
+            (Some(n.rebalance()), removed)
+        } else if index > left_size {
+            let (right, removed) = Self::remove_at(n.right.take(), index - left_size - 1);
+            n.right = right;
+            (Some(n.rebalance()), removed)
+        } else {
+            match (n.left.take(), n.right.take()) {
+                (None, None) => (None, n.piece),
+                (Some(left), None) => (Some(left), n.piece),
+                (None, Some(right)) => (Some(right), n.piece),
+                (Some(left), Some(right)) => {
+                    let (new_right, successor) = Self::remove_leftmost(right);
+                    let mut replacement = Box::new(Node::new(successor));
+                    replacement.left = Some(left);
+                    replacement.right = new_right;
+                    (Some(replacement.rebalance()), n.piece)
+                }
+            }
+        }
+    }
+
+    fn remove_leftmost(node: Box<Node>) -> (Option<Box<Node>>, Piece) {
+        let mut n = node;
+        match n.left.take() {
+            None => (n.right.take(), n.piece),
+            Some(left) => {
+                let (new_left, piece) = Self::remove_leftmost(left);
+                n.left = new_left;
+                (Some(n.rebalance()), piece)
+            }
+        }
+    }
+
+    fn get(node: &Option<Box<Node>>, index: usize) -> &Piece {
+        let n = node.as_ref().expect("piece index out of bounds");
+        let left_size = Self::size(&n.left);
+
+        if index < left_size {
+            Self::get(&n.left, index)
+        } else if index > left_size {
+            Self::get(&n.right, index - left_size - 1)
+        } else {
+            &n.piece
+        }
+    }
+
+    /// Overwrite the piece at `index`'s length, recomputing `subtree_len` on
+    /// the way back up the path so ancestors' cached totals stay correct.
+    /// This is the only sanctioned way to change `Piece::length` in place:
+    /// exposing `&mut Piece` directly (e.g. via `IndexMut`) would let a
+    /// caller edit `length` without anyone fixing up the cache.
+    fn set_length(node: &mut Option<Box<Node>>, index: usize, new_length: usize) {
+        let n = node.as_mut().expect("piece index out of bounds");
+        let left_size = Self::size(&n.left);
+
+        if index < left_size {
+            Self::set_length(&mut n.left, index, new_length);
+        } else if index > left_size {
+            Self::set_length(&mut n.right, index - left_size - 1, new_length);
+        } else {
+            n.piece.length = new_length;
+        }
+
+        n.subtree_len = n.piece.length + Self::subtree_len(&n.left) + Self::subtree_len(&n.right);
+    }
+
+    /// Overwrite the piece at `index`'s start offset. `start` isn't part of
+    /// any cached aggregate, so there's nothing to recompute on the way back.
+    fn set_start(node: &mut Option<Box<Node>>, index: usize, new_start: usize) {
+        let n = node.as_mut().expect("piece index out of bounds");
+        let left_size = Self::size(&n.left);
+
+        if index < left_size {
+            Self::set_start(&mut n.left, index, new_start);
+        } else if index > left_size {
+            Self::set_start(&mut n.right, index - left_size - 1, new_start);
+        } else {
+            n.piece.start = new_start;
+        }
+    }
+
+    /// Descend by accumulated logical length (not node count) to find the
+    /// piece covering `position`. Returns the piece's in-order index and the
+    /// offset inside it.
+    fn locate(node: &Option<Box<Node>>, position: usize) -> (usize, usize) {
+        let n = node.as_ref().expect("position out of bounds");
+        let left_len = Self::subtree_len(&n.left);
+
+        if position < left_len {
+            Self::locate(&n.left, position)
+        } else {
+            let remainder = position - left_len;
+            if remainder < n.piece.length {
+                (Self::size(&n.left), remainder)
+            } else {
+                let (idx, offset) = Self::locate(&n.right, remainder - n.piece.length);
+                (Self::size(&n.left) + 1 + idx, offset)
+            }
+        }
+    }
+
+    fn in_order<'a>(node: &'a Option<Box<Node>>, out: &mut Vec<&'a Piece>) {
+        if let Some(n) = node {
+            Self::in_order(&n.left, out);
+            out.push(&n.piece);
+            Self::in_order(&n.right, out);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PieceTree {
+    root: Option<Box<Node>>,
+}
+
+impl PieceTree {
+    pub(crate) fn new() -> Self {
+        PieceTree { root: None }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        Node::size(&self.root)
+    }
+
+    pub(crate) fn total_len(&self) -> usize {
+        Node::subtree_len(&self.root)
+    }
+
+    /// No-op: a boxed-node tree has nothing equivalent to `Vec`'s spare
+    /// capacity, this only exists so `PieceTree` is a drop-in for `Vec<Piece>`.
+    pub(crate) fn reserve(&mut self, _additional: usize) {}
+
+    pub(crate) fn push(&mut self, piece: Piece) {
+        let len = self.len();
+        self.insert(len, piece);
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, piece: Piece) {
+        self.root = Some(Node::insert_at(self.root.take(), index, piece));
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) -> Piece {
+        let (root, piece) = Node::remove_at(self.root.take(), index);
+        self.root = root;
+        piece
+    }
+
+    pub(crate) fn drain(&mut self, range: Range<usize>) -> Vec<Piece> {
+        let count = range.end.saturating_sub(range.start);
+        let mut removed = Vec::with_capacity(count);
+        for _ in 0..count {
+            removed.push(self.remove(range.start));
+        }
+        removed
+    }
+
+    pub(crate) fn last(&self) -> Option<&Piece> {
+        if self.root.is_none() {
+            None
+        } else {
+            Some(Node::get(&self.root, self.len() - 1))
+        }
+    }
+
+    pub(crate) fn iter(&self) -> std::vec::IntoIter<&Piece> {
+        let mut out = Vec::with_capacity(self.len());
+        Node::in_order(&self.root, &mut out);
+        out.into_iter()
+    }
+
+    /// Find the piece covering logical `position` in O(log n), returning its
+    /// in-order index and the offset inside it. Mirrors the fallback used by
+    /// the linear scan: `(len(), 0)` when `position` is at or past the end.
+    pub(crate) fn locate(&self, position: usize) -> (usize, usize) {
+        if position >= self.total_len() {
+            (self.len(), 0)
+        } else {
+            Node::locate(&self.root, position)
+        }
+    }
+
+    /// Update the piece at `index`'s length in place, keeping every
+    /// ancestor's cached `subtree_len` consistent. There is deliberately no
+    /// `IndexMut` impl: a raw `&mut Piece` would let a caller change
+    /// `length` without anyone recomputing the cache, and `locate` would
+    /// silently start returning wrong positions.
+    pub(crate) fn set_length(&mut self, index: usize, new_length: usize) {
+        Node::set_length(&mut self.root, index, new_length);
+    }
+
+    /// Update the piece at `index`'s start offset in place.
+    pub(crate) fn set_start(&mut self, index: usize, new_start: usize) {
+        Node::set_start(&mut self.root, index, new_start);
+    }
+}
+
+impl Index<usize> for PieceTree {
+    type Output = Piece;
+
+    fn index(&self, index: usize) -> &Piece {
+        Node::get(&self.root, index)
+    }
+}
@@ -1,21 +1,40 @@
 use piecetable::PieceTable;
 
-fn main() {
-    let mut pt = PieceTable::from_str("HolaMatias.");
-    println!("{}", pt.display_result().unwrap());
+fn content(pt: &mut PieceTable<u8>) -> String {
+    String::from_utf8(pt.iter().copied().collect()).unwrap()
+}
+
+#[test]
+fn sequential_inserts_match_a_plain_string() {
+    let mut pt = PieceTable::<u8>::from_str("HolaMatias.");
+    let mut expected = String::from("HolaMatias.");
+    assert_eq!(content(&mut pt), expected);
+
+    pt.insert_slice(b",", 4);
+    expected.insert(4, ',');
+    assert_eq!(content(&mut pt), expected);
 
-    pt.insert(",", 4);
-    println!("{}", pt.display_result().unwrap());
+    pt.insert_slice(b" ", 5);
+    expected.insert(5, ' ');
+    assert_eq!(content(&mut pt), expected);
 
-    pt.insert(" ", 5);
-    println!("{}", pt.display_result().unwrap());
+    pt.insert_slice(b"Hola, Martin. ", 0);
+    expected.insert_str(0, "Hola, Martin. ");
+    assert_eq!(content(&mut pt), expected);
 
-    pt.insert("Hola, Martin. ", 0);
-    println!("{}", pt.display_result().unwrap());
+    pt.insert_slice(b", buenos dias", 18);
+    expected.insert_str(18, ", buenos dias");
+    assert_eq!(content(&mut pt), expected);
+
+    pt.insert_slice(b" Saludos!", 40);
+    expected.insert_str(40, " Saludos!");
+    assert_eq!(content(&mut pt), expected);
+}
 
-    pt.insert(", buenos dias", 18);
-    println!("{}", pt.display_result().unwrap());
+#[test]
+fn single_element_insert_splits_the_covering_piece() {
+    let mut pt = PieceTable::<u8>::from_str("Buenos dias, que buen clima hoy");
+    pt.insert(b'M', 11);
 
-    pt.insert(" Saludos!", 40);
-    println!("{}", pt.display_result().unwrap());
+    assert_eq!(content(&mut pt), "Buenos diasM, que buen clima hoy");
 }
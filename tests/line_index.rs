@@ -0,0 +1,100 @@
+use piecetable::PieceTable;
+
+fn content(pt: &mut PieceTable<u8>) -> String {
+    String::from_utf8(pt.iter().copied().collect()).unwrap()
+}
+
+#[test]
+fn line_count_and_offsets_match_a_freshly_built_index() {
+    let mut pt = PieceTable::<u8>::from_str("one\ntwo\nthree");
+    pt.enable_default_line_index();
+
+    assert_eq!(pt.line_count(), 3);
+    assert_eq!(pt.line_to_offset(0), 0);
+    assert_eq!(pt.line_to_offset(1), 4);
+    assert_eq!(pt.line_to_offset(2), 8);
+}
+
+#[test]
+fn line_slice_excludes_the_trailing_separator() {
+    let mut pt = PieceTable::<u8>::from_str("one\ntwo\nthree");
+    pt.enable_default_line_index();
+
+    let line0: Vec<u8> = pt.line_slice(0).copied().collect();
+    let line1: Vec<u8> = pt.line_slice(1).copied().collect();
+    let line2: Vec<u8> = pt.line_slice(2).copied().collect();
+
+    assert_eq!(line0, b"one");
+    assert_eq!(line1, b"two");
+    assert_eq!(line2, b"three");
+}
+
+#[test]
+fn offset_to_line_col_round_trips_with_line_to_offset() {
+    let mut pt = PieceTable::<u8>::from_str("one\ntwo\nthree");
+    pt.enable_default_line_index();
+
+    assert_eq!(pt.offset_to_line_col(0), (0, 0));
+    assert_eq!(pt.offset_to_line_col(5), (1, 1));
+    assert_eq!(pt.offset_to_line_col(10), (2, 2));
+}
+
+#[test]
+fn inserting_a_separator_incrementally_splits_a_line() {
+    let mut pt = PieceTable::<u8>::from_str("onetwo");
+    pt.enable_default_line_index();
+    assert_eq!(pt.line_count(), 1);
+
+    pt.insert(b'\n', 3);
+    assert_eq!(content(&mut pt), "one\ntwo");
+    assert_eq!(pt.line_count(), 2);
+    assert_eq!(pt.line_to_offset(1), 4);
+
+    let line0: Vec<u8> = pt.line_slice(0).copied().collect();
+    let line1: Vec<u8> = pt.line_slice(1).copied().collect();
+    assert_eq!(line0, b"one");
+    assert_eq!(line1, b"two");
+}
+
+#[test]
+fn deleting_a_separator_incrementally_merges_lines() {
+    let mut pt = PieceTable::<u8>::from_str("one\ntwo");
+    pt.enable_default_line_index();
+    assert_eq!(pt.line_count(), 2);
+
+    pt.delete(3);
+    assert_eq!(content(&mut pt), "onetwo");
+    assert_eq!(pt.line_count(), 1);
+    assert_eq!(pt.line_to_offset(0), 0);
+}
+
+#[test]
+fn deleting_a_range_spanning_a_separator_drops_the_swallowed_line_start() {
+    let mut pt = PieceTable::<u8>::from_str("one\ntwo\nthree");
+    pt.enable_default_line_index();
+
+    pt.delete_range(2..6);
+    assert_eq!(content(&mut pt), "ono\nthree");
+    assert_eq!(pt.line_count(), 2);
+    assert_eq!(pt.line_to_offset(1), 4);
+}
+
+#[test]
+fn enabling_the_line_index_builds_it_lazily_on_src_and_disabling_clears_it() {
+    let mut pt = PieceTable::<u8>::new();
+    pt.enable_default_line_index();
+    pt.src(b"a\nb\nc");
+
+    assert_eq!(pt.line_count(), 3);
+
+    pt.disable_line_index();
+    assert_eq!(pt.line_count(), 0);
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn line_to_offset_panics_past_the_last_line() {
+    let mut pt = PieceTable::<u8>::from_str("one\ntwo");
+    pt.enable_default_line_index();
+    pt.line_to_offset(5);
+}
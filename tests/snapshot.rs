@@ -0,0 +1,157 @@
+use piecetable::PieceTable;
+
+fn content(pt: &mut PieceTable<u8>) -> String {
+    String::from_utf8(pt.iter().copied().collect()).unwrap()
+}
+
+#[test]
+fn round_trips_content() {
+    let source = "Buenos dias, amigo";
+    let mut pt = PieceTable::<u8>::from_str(source);
+    pt.insert_slice(b" hola", 12);
+
+    let bytes = pt.to_bytes();
+    let mut restored = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap();
+
+    assert_eq!(content(&mut restored), content(&mut pt));
+}
+
+#[test]
+fn round_trips_undo_redo_history() {
+    let source = "Buenos dias";
+    let mut pt = PieceTable::<u8>::from_str(source);
+    pt.insert_slice(b" amigo", 11);
+    pt.delete_range(0..7);
+    pt.undo();
+
+    let bytes = pt.to_bytes();
+    let mut restored = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap();
+
+    assert_eq!(content(&mut restored), content(&mut pt));
+    assert!(restored.can_undo());
+    assert!(restored.can_redo());
+
+    restored.undo();
+    assert_eq!(content(&mut restored), source);
+
+    restored.redo();
+    restored.redo();
+    assert_eq!(content(&mut restored), "dias amigo");
+}
+
+#[test]
+fn rejects_a_read_buffer_of_the_wrong_length() {
+    let source = "Buenos dias";
+    let pt = PieceTable::<u8>::from_str(source);
+    let bytes = pt.to_bytes();
+
+    let err = PieceTable::from_bytes(&bytes, b"short").unwrap_err();
+    assert!(matches!(
+        err,
+        piecetable::SnapshotError::ReadBufferLengthMismatch {
+            expected: 11,
+            found: 5
+        }
+    ));
+}
+
+#[test]
+fn rejects_truncated_bytes() {
+    let source = "Buenos dias";
+    let pt = PieceTable::<u8>::from_str(source);
+    let bytes = pt.to_bytes();
+
+    let err = PieceTable::from_bytes(&bytes[..bytes.len() - 1], source.as_bytes()).unwrap_err();
+    assert!(matches!(err, piecetable::SnapshotError::UnexpectedEof));
+}
+
+// Byte offsets in a snapshot taken from a freshly built `from_str` table
+// with no edits (so there is exactly one read piece and empty undo/redo
+// stacks), per the format documented in `src/snapshot.rs`:
+// read_len(8), add_len(8), add_buf(0), logical_len(8), piece_count(8),
+// then the one piece's tag(1), start(8), length(8).
+const LOGICAL_LEN: usize = 16;
+const PIECE_TAG: usize = 32;
+const PIECE_LENGTH: usize = PIECE_TAG + 1 + 8;
+
+#[test]
+fn rejects_an_invalid_buffer_tag() {
+    let source = "Buenos dias";
+    let pt = PieceTable::<u8>::from_str(source);
+    let mut bytes = pt.to_bytes();
+
+    bytes[PIECE_TAG] = 7;
+
+    let err = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap_err();
+    assert!(matches!(err, piecetable::SnapshotError::InvalidBufferTag(7)));
+}
+
+#[test]
+fn rejects_a_piece_whose_span_overruns_its_buffer() {
+    let source = "Buenos dias";
+    let pt = PieceTable::<u8>::from_str(source);
+    let mut bytes = pt.to_bytes();
+
+    bytes[PIECE_LENGTH..PIECE_LENGTH + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    let err = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap_err();
+    assert!(matches!(
+        err,
+        piecetable::SnapshotError::PieceOutOfBounds { index: 0 }
+    ));
+}
+
+#[test]
+fn rejects_a_logical_length_that_does_not_match_the_pieces() {
+    let source = "Buenos dias";
+    let pt = PieceTable::<u8>::from_str(source);
+    let mut bytes = pt.to_bytes();
+
+    bytes[LOGICAL_LEN..LOGICAL_LEN + 8].copy_from_slice(&999u64.to_le_bytes());
+
+    let err = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap_err();
+    assert!(matches!(
+        err,
+        piecetable::SnapshotError::LogicalLengthMismatch {
+            expected: 999,
+            found: 11
+        }
+    ));
+}
+
+#[test]
+fn rejects_an_undo_edit_whose_add_buffer_span_is_out_of_bounds() {
+    let source = "Buenos dias";
+    let mut pt = PieceTable::<u8>::from_str(source);
+    pt.insert_slice(b"!", 11);
+
+    let mut bytes = pt.to_bytes();
+    // The sole undo edit (an Insert) is the last thing written before the
+    // empty redo stack's count, and its length field is its own last 8
+    // bytes: [.., tag(1), position(8), add_start(8), length(8)], redo_count(8).
+    let n = bytes.len();
+    let length_field = n - 8 - 8;
+    bytes[length_field..length_field + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    let err = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap_err();
+    assert!(matches!(
+        err,
+        piecetable::SnapshotError::EditOutOfBounds { index: 0 }
+    ));
+}
+
+#[test]
+fn rejects_an_invalid_edit_tag() {
+    let source = "Buenos dias";
+    let mut pt = PieceTable::<u8>::from_str(source);
+    pt.insert_slice(b"!", 11);
+
+    let mut bytes = pt.to_bytes();
+    // tag, position(8), add_start(8), length(8), then redo_count(8).
+    let n = bytes.len();
+    let tag_field = n - 8 - 8 - 8 - 8 - 1;
+    bytes[tag_field] = 9;
+
+    let err = PieceTable::from_bytes(&bytes, source.as_bytes()).unwrap_err();
+    assert!(matches!(err, piecetable::SnapshotError::InvalidEditTag(9)));
+}
@@ -0,0 +1,92 @@
+//! Runs a long scripted sequence of edits against `PieceTable<u8>` and
+//! checks every intermediate state against a plain `Vec<u8>` reference
+//! model. The backing piece store (the order-statistics `PieceTree` by
+//! default, or a flat `Vec<Piece>` under the `vec-pieces` feature) is an
+//! implementation detail neither this test nor the public API can see, so
+//! running this same file under both feature configurations is what
+//! demonstrates the two stores agree.
+
+use piecetable::PieceTable;
+
+fn content(pt: &mut PieceTable<u8>) -> Vec<u8> {
+    pt.iter().copied().collect()
+}
+
+#[test]
+fn scripted_edits_match_a_vec_reference_model() {
+    let mut pt = PieceTable::<u8>::from_str("");
+    let mut model: Vec<u8> = Vec::new();
+
+    let insert_slice = |pt: &mut PieceTable<u8>, model: &mut Vec<u8>, s: &[u8], at: usize| {
+        pt.insert_slice(s, at);
+        model.splice(at..at, s.iter().copied());
+        assert_eq!(content(pt), *model);
+    };
+
+    let delete_range =
+        |pt: &mut PieceTable<u8>, model: &mut Vec<u8>, range: std::ops::Range<usize>| {
+            pt.delete_range(range.clone());
+            model.splice(range, std::iter::empty());
+            assert_eq!(content(pt), *model);
+        };
+
+    let replace_range = |pt: &mut PieceTable<u8>,
+                          model: &mut Vec<u8>,
+                          range: std::ops::Range<usize>,
+                          s: &[u8]| {
+        pt.replace_range(range.clone(), s);
+        model.splice(range, s.iter().copied());
+        assert_eq!(content(pt), *model);
+    };
+
+    // Interleave many small inserts at varied positions, forcing repeated
+    // piece splits so the store's cached subtree/positional bookkeeping
+    // (whichever store is compiled in) gets exercised at every level.
+    insert_slice(&mut pt, &mut model, b"the quick brown fox", 0);
+    insert_slice(&mut pt, &mut model, b" lazy", 9);
+    insert_slice(&mut pt, &mut model, b"jumps over ", 15);
+    insert_slice(&mut pt, &mut model, b"extremely ", 4);
+    let end = model.len();
+    insert_slice(&mut pt, &mut model, b"the dog", end);
+    insert_slice(&mut pt, &mut model, b"!!!", 0);
+
+    delete_range(&mut pt, &mut model, 0..3);
+    delete_range(&mut pt, &mut model, 10..20);
+    replace_range(&mut pt, &mut model, 0..3, b"a");
+    let tail_start = model.len() - 3;
+    let tail_end = model.len();
+    replace_range(&mut pt, &mut model, tail_start..tail_end, b"canine");
+
+    for i in 0..20 {
+        let at = (i * 7) % (model.len() + 1);
+        insert_slice(&mut pt, &mut model, b"x", at);
+    }
+
+    for i in 0..10 {
+        let at = (i * 11) % model.len();
+        let end = (at + 2).min(model.len());
+        delete_range(&mut pt, &mut model, at..end);
+    }
+
+    assert_eq!(content(&mut pt), model);
+    assert_eq!(pt.len(), model.len());
+}
+
+#[test]
+fn single_element_insert_and_delete_match_a_vec_reference_model() {
+    let mut pt = PieceTable::<u8>::from_str("abcdefghij");
+    let mut model: Vec<u8> = b"abcdefghij".to_vec();
+
+    for (element, at) in [(b'1', 0), (b'2', 5), (b'3', model.len()), (b'4', 3)] {
+        pt.insert(element, at);
+        model.insert(at, element);
+        assert_eq!(content(&mut pt), model);
+    }
+
+    for _ in 0..3 {
+        let at = model.len() / 2;
+        model.remove(at);
+        pt.delete(at);
+        assert_eq!(content(&mut pt), model);
+    }
+}
@@ -0,0 +1,69 @@
+use piecetable::PieceTable;
+
+fn content(pt: &mut PieceTable<u8>) -> String {
+    String::from_utf8(pt.iter().copied().collect()).unwrap()
+}
+
+#[test]
+fn undo_reverts_insert_and_redo_replays_it() {
+    let mut pt = PieceTable::<u8>::from_str("Buenos dias");
+    assert!(!pt.can_undo());
+
+    pt.insert_slice(b" amigo", 11);
+    assert_eq!(content(&mut pt), "Buenos dias amigo");
+
+    assert!(pt.can_undo());
+    assert!(pt.undo());
+    assert_eq!(content(&mut pt), "Buenos dias");
+    assert!(!pt.can_undo());
+
+    assert!(pt.can_redo());
+    assert!(pt.redo());
+    assert_eq!(content(&mut pt), "Buenos dias amigo");
+    assert!(!pt.can_redo());
+}
+
+#[test]
+fn undo_reverts_delete_and_redo_replays_it() {
+    let mut pt = PieceTable::<u8>::from_str("Buenos dias amigo");
+    pt.delete_range(7..12);
+    assert_eq!(content(&mut pt), "Buenos amigo");
+
+    assert!(pt.undo());
+    assert_eq!(content(&mut pt), "Buenos dias amigo");
+
+    assert!(pt.redo());
+    assert_eq!(content(&mut pt), "Buenos amigo");
+}
+
+#[test]
+fn undo_reverts_replace_and_redo_replays_it() {
+    let mut pt = PieceTable::<u8>::from_str("Buenos dias amigo");
+    pt.replace_range(7..12, b"tardes");
+    assert_eq!(content(&mut pt), "Buenos tardesamigo");
+
+    assert!(pt.undo());
+    assert_eq!(content(&mut pt), "Buenos dias amigo");
+
+    assert!(pt.redo());
+    assert_eq!(content(&mut pt), "Buenos tardesamigo");
+}
+
+#[test]
+fn undoing_past_the_bottom_of_the_stack_is_a_no_op() {
+    let mut pt = PieceTable::<u8>::from_str("Buenos dias");
+    assert!(!pt.undo());
+    assert_eq!(content(&mut pt), "Buenos dias");
+}
+
+#[test]
+fn a_new_edit_after_undo_clears_the_redo_stack() {
+    let mut pt = PieceTable::<u8>::from_str("Buenos dias");
+    pt.insert_slice(b" amigo", 11);
+    assert!(pt.undo());
+    assert!(pt.can_redo());
+
+    pt.insert_slice(b" amiga", 11);
+    assert!(!pt.can_redo());
+    assert_eq!(content(&mut pt), "Buenos dias amiga");
+}